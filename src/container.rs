@@ -0,0 +1,397 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use sha2::{Digest, Sha256};
+
+use crate::padding::PaddingMode;
+use crate::{FirmwareError, FirmwarePart};
+
+pub const MAGIC: &[u8; 4] = b"RNSC";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    Zstd,
+    Lzma,
+    Bzip2,
+}
+
+impl Codec {
+    fn to_tag(self) -> u8 {
+        match self {
+            Codec::None => 0,
+            Codec::Zstd => 1,
+            Codec::Lzma => 2,
+            Codec::Bzip2 => 3,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Result<Self, FirmwareError> {
+        match tag {
+            0 => Ok(Codec::None),
+            1 => Ok(Codec::Zstd),
+            2 => Ok(Codec::Lzma),
+            3 => Ok(Codec::Bzip2),
+            other => Err(FirmwareError::Parse(format!("Unknown codec tag: {}", other))),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, FirmwareError> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Ok(Codec::None),
+            "zstd" => Ok(Codec::Zstd),
+            "lzma" | "xz" => Ok(Codec::Lzma),
+            "bzip2" | "bz2" => Ok(Codec::Bzip2),
+            other => Err(FirmwareError::Config(format!("Unknown codec: {}", other))),
+        }
+    }
+}
+
+fn compress(codec: Codec, data: &[u8]) -> Result<Vec<u8>, FirmwareError> {
+    match codec {
+        Codec::None => Ok(data.to_vec()),
+        #[cfg(feature = "zstd")]
+        Codec::Zstd => zstd::encode_all(data, 0)
+            .map_err(|e| FirmwareError::Parse(format!("zstd compress failed: {}", e))),
+        #[cfg(not(feature = "zstd"))]
+        Codec::Zstd => Err(FirmwareError::Config("built without the 'zstd' feature".into())),
+        #[cfg(feature = "lzma")]
+        Codec::Lzma => {
+            let mut out = Vec::new();
+            xz2::read::XzEncoder::new(data, 6)
+                .read_to_end(&mut out)
+                .map_err(|e| FirmwareError::Parse(format!("lzma compress failed: {}", e)))?;
+            Ok(out)
+        }
+        #[cfg(not(feature = "lzma"))]
+        Codec::Lzma => Err(FirmwareError::Config("built without the 'lzma' feature".into())),
+        #[cfg(feature = "bzip2")]
+        Codec::Bzip2 => {
+            let mut out = Vec::new();
+            bzip2::read::BzEncoder::new(data, bzip2::Compression::best())
+                .read_to_end(&mut out)
+                .map_err(|e| FirmwareError::Parse(format!("bzip2 compress failed: {}", e)))?;
+            Ok(out)
+        }
+        #[cfg(not(feature = "bzip2"))]
+        Codec::Bzip2 => Err(FirmwareError::Config("built without the 'bzip2' feature".into())),
+    }
+}
+
+fn decompress(codec: Codec, data: &[u8], uncompressed_size: u64) -> Result<Vec<u8>, FirmwareError> {
+    match codec {
+        Codec::None => Ok(data.to_vec()),
+        #[cfg(feature = "zstd")]
+        Codec::Zstd => zstd::decode_all(data)
+            .map_err(|e| FirmwareError::Parse(format!("zstd decompress failed: {}", e))),
+        #[cfg(not(feature = "zstd"))]
+        Codec::Zstd => Err(FirmwareError::Config("built without the 'zstd' feature".into())),
+        #[cfg(feature = "lzma")]
+        Codec::Lzma => {
+            let mut out = Vec::with_capacity(uncompressed_size as usize);
+            xz2::read::XzDecoder::new(data)
+                .read_to_end(&mut out)
+                .map_err(|e| FirmwareError::Parse(format!("lzma decompress failed: {}", e)))?;
+            Ok(out)
+        }
+        #[cfg(not(feature = "lzma"))]
+        Codec::Lzma => Err(FirmwareError::Config("built without the 'lzma' feature".into())),
+        #[cfg(feature = "bzip2")]
+        Codec::Bzip2 => {
+            let mut out = Vec::with_capacity(uncompressed_size as usize);
+            bzip2::read::BzDecoder::new(data)
+                .read_to_end(&mut out)
+                .map_err(|e| FirmwareError::Parse(format!("bzip2 decompress failed: {}", e)))?;
+            Ok(out)
+        }
+        #[cfg(not(feature = "bzip2"))]
+        Codec::Bzip2 => Err(FirmwareError::Config("built without the 'bzip2' feature".into())),
+        #[allow(unreachable_patterns)]
+        _ => {
+            let _ = uncompressed_size;
+            unreachable!()
+        }
+    }
+}
+
+struct TocEntry {
+    name: String,
+    uncompressed_offset: u64,
+    uncompressed_size: u64,
+    compressed_offset: u64,
+    compressed_size: u64,
+    codec: Codec,
+    sha256: [u8; 32],
+}
+
+fn write_u64(out: &mut Vec<u8>, v: u64) {
+    out.extend_from_slice(&v.to_le_bytes());
+}
+
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    write_u64(out, s.len() as u64);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn read_u64(buf: &[u8], pos: &mut usize) -> Result<u64, FirmwareError> {
+    if *pos + 8 > buf.len() {
+        return Err(FirmwareError::Parse("Truncated container header".into()));
+    }
+    let v = u64::from_le_bytes(buf[*pos..*pos + 8].try_into().unwrap());
+    *pos += 8;
+    Ok(v)
+}
+
+fn read_string(buf: &[u8], pos: &mut usize) -> Result<String, FirmwareError> {
+    let len = read_u64(buf, pos)? as usize;
+    if *pos + len > buf.len() {
+        return Err(FirmwareError::Parse("Truncated container string".into()));
+    }
+    let s = String::from_utf8(buf[*pos..*pos + len].to_vec())
+        .map_err(|e| FirmwareError::Parse(format!("Invalid UTF-8 in container TOC: {}", e)))?;
+    *pos += len;
+    Ok(s)
+}
+
+fn fill_padding(buf: &mut [u8], mode: &PaddingMode) {
+    if let PaddingMode::Lfg { seed } = *mode {
+        crate::padding::Lfg::new(seed).fill(buf);
+    }
+}
+
+pub fn pack_compressed(
+    firmware_path: &Path,
+    parts: &[FirmwarePart],
+    codec: Codec,
+) -> Result<(), FirmwareError> {
+    let mut payloads = Vec::with_capacity(parts.len());
+
+    for part in parts {
+        let mut data = Vec::new();
+        match File::open(format!("{}.bin", part.name)) {
+            Ok(mut input) => {
+                input.read_to_end(&mut data)?;
+                data.truncate(part.size as usize);
+                if (data.len() as u64) < part.size {
+                    let fill_start = data.len();
+                    data.resize(part.size as usize, part.padding_byte);
+                    fill_padding(&mut data[fill_start..], &part.padding_mode);
+                }
+            }
+            Err(_) => {
+                println!("Warning: {}.bin not found, packing as padding", part.name);
+                data = vec![part.padding_byte; part.size as usize];
+                fill_padding(&mut data, &part.padding_mode);
+            }
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(&data);
+        let sha256: [u8; 32] = hasher.finalize().into();
+
+        let compressed = compress(codec, &data)?;
+        payloads.push((compressed, sha256));
+    }
+
+    let mut toc = Vec::with_capacity(parts.len());
+    let mut compressed_offset = 0u64;
+    for (part, (compressed, sha256)) in parts.iter().zip(&payloads) {
+        toc.push(TocEntry {
+            name: part.name.clone(),
+            uncompressed_offset: part.offset,
+            uncompressed_size: part.size,
+            compressed_offset,
+            compressed_size: compressed.len() as u64,
+            codec,
+            sha256: *sha256,
+        });
+        compressed_offset += compressed.len() as u64;
+    }
+
+    let mut toc_bytes = Vec::new();
+    write_u64(&mut toc_bytes, toc.len() as u64);
+    for entry in &toc {
+        write_string(&mut toc_bytes, &entry.name);
+        write_u64(&mut toc_bytes, entry.uncompressed_offset);
+        write_u64(&mut toc_bytes, entry.uncompressed_size);
+        write_u64(&mut toc_bytes, entry.compressed_offset);
+        write_u64(&mut toc_bytes, entry.compressed_size);
+        toc_bytes.push(entry.codec.to_tag());
+        toc_bytes.extend_from_slice(&entry.sha256);
+    }
+
+    let mut firmware = File::create(firmware_path)?;
+    firmware.write_all(MAGIC)?;
+    firmware.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    firmware.write_all(&(toc_bytes.len() as u64).to_le_bytes())?;
+    firmware.write_all(&toc_bytes)?;
+    for (compressed, _) in &payloads {
+        firmware.write_all(compressed)?;
+    }
+
+    for entry in &toc {
+        println!(
+            "Packed {}: {} -> {} bytes ({:?}), SHA256: {}",
+            entry.name,
+            entry.uncompressed_size,
+            entry.compressed_size,
+            entry.codec,
+            hex(&entry.sha256)
+        );
+    }
+
+    Ok(())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn is_compressed_container(firmware_path: &Path) -> io::Result<bool> {
+    let mut file = File::open(firmware_path)?;
+    let mut magic = [0u8; 4];
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn unpack_compressed(firmware_path: &Path) -> Result<(), FirmwareError> {
+    let mut firmware = File::open(firmware_path)?;
+
+    let mut magic = [0u8; 4];
+    firmware.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(FirmwareError::Parse("Bad container magic".into()));
+    }
+
+    let mut version_buf = [0u8; 4];
+    firmware.read_exact(&mut version_buf)?;
+    let version = u32::from_le_bytes(version_buf);
+    if version != FORMAT_VERSION {
+        return Err(FirmwareError::Parse(format!(
+            "Unsupported container version: {}",
+            version
+        )));
+    }
+
+    let mut toc_len_buf = [0u8; 8];
+    firmware.read_exact(&mut toc_len_buf)?;
+    let toc_len = u64::from_le_bytes(toc_len_buf);
+
+    let available = firmware.metadata()?.len().saturating_sub(firmware.stream_position()?);
+    crate::check_claimed_len(toc_len, available, "container TOC length")?;
+
+    let mut header = vec![0u8; toc_len as usize];
+    firmware.read_exact(&mut header)?;
+
+    let mut pos = 0usize;
+    let count = read_u64(&header, &mut pos)?;
+    // Each TOC entry is at least this many bytes (name_len, 4 u64s, codec tag, sha256),
+    // not counting the variable-length name itself.
+    const MIN_ENTRY_SIZE: u64 = 8 + 8 + 8 + 8 + 8 + 1 + 32;
+    crate::check_claimed_len(count.saturating_mul(MIN_ENTRY_SIZE), header.len() as u64, "container TOC entry count")?;
+    let mut toc = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let name = read_string(&header, &mut pos)?;
+        let uncompressed_offset = read_u64(&header, &mut pos)?;
+        let uncompressed_size = read_u64(&header, &mut pos)?;
+        let compressed_offset = read_u64(&header, &mut pos)?;
+        let compressed_size = read_u64(&header, &mut pos)?;
+        if pos + 1 > header.len() {
+            return Err(FirmwareError::Parse("Truncated container TOC".into()));
+        }
+        let codec = Codec::from_tag(header[pos])?;
+        pos += 1;
+        if pos + 32 > header.len() {
+            return Err(FirmwareError::Parse("Truncated container TOC".into()));
+        }
+        let mut sha256 = [0u8; 32];
+        sha256.copy_from_slice(&header[pos..pos + 32]);
+        pos += 32;
+
+        toc.push(TocEntry {
+            name,
+            uncompressed_offset,
+            uncompressed_size,
+            compressed_offset,
+            compressed_size,
+            codec,
+            sha256,
+        });
+    }
+
+    let payload_start = firmware.stream_position()?;
+    let mut mismatches = 0;
+
+    for entry in &toc {
+        firmware.seek(SeekFrom::Start(payload_start + entry.compressed_offset))?;
+        let mut compressed = vec![0u8; entry.compressed_size as usize];
+        firmware.read_exact(&mut compressed)?;
+
+        let data = decompress(entry.codec, &compressed, entry.uncompressed_size)?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(&data);
+        let actual: [u8; 32] = hasher.finalize().into();
+
+        let mut output = File::create(format!("{}.bin", entry.name))?;
+        output.write_all(&data)?;
+
+        let status = if actual == entry.sha256 { "OK" } else { "MISMATCH" };
+        println!(
+            "Extracted {}: {} bytes at 0x{:x}, SHA256 {} ({})",
+            entry.name,
+            data.len(),
+            entry.uncompressed_offset,
+            hex(&actual),
+            status
+        );
+        if actual != entry.sha256 {
+            mismatches += 1;
+        }
+    }
+
+    if mismatches > 0 {
+        Err(FirmwareError::Parse(format!(
+            "{} mismatch(es) found while unpacking container",
+            mismatches
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn pack_unpack_round_trip() {
+        let data = b"container round trip payload";
+        fs::write("container_rt_part.bin", data).unwrap();
+
+        let parts = [FirmwarePart {
+            name: "container_rt_part".to_string(),
+            offset: 0,
+            size: data.len() as u64,
+            padding_byte: 0xFF,
+            padding_mode: PaddingMode::Constant(0xFF),
+            use_custom_padding: false,
+            has_explicit_size: true,
+        }];
+
+        let firmware_path = Path::new("container_rt.rnsc");
+        pack_compressed(firmware_path, &parts, Codec::None).unwrap();
+        assert!(is_compressed_container(firmware_path).unwrap());
+
+        unpack_compressed(firmware_path).unwrap();
+        assert_eq!(fs::read("container_rt_part.bin").unwrap(), data);
+
+        fs::remove_file(firmware_path).unwrap();
+        fs::remove_file("container_rt_part.bin").unwrap();
+    }
+}
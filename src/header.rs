@@ -0,0 +1,161 @@
+use std::io::Read;
+
+use crate::{FirmwareError, FirmwarePart};
+
+pub const MAGIC: &[u8; 4] = b"RNFH";
+pub const FORMAT_VERSION: u32 = 1;
+
+pub(crate) struct FirmwareHeader {
+    pub(crate) version: u32,
+    pub(crate) declared_size: u64,
+    pub(crate) parts: Option<Vec<FirmwarePart>>,
+}
+
+fn read_exact_vec<R: Read>(reader: &mut R, len: usize) -> Result<Vec<u8>, FirmwareError> {
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, FirmwareError> {
+    let buf = read_exact_vec(reader, 4)?;
+    Ok(u32::from_le_bytes(buf.try_into().unwrap()))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, FirmwareError> {
+    let buf = read_exact_vec(reader, 8)?;
+    Ok(u64::from_le_bytes(buf.try_into().unwrap()))
+}
+
+fn read_string<R: Read>(reader: &mut R) -> Result<String, FirmwareError> {
+    let len = read_u64(reader)? as usize;
+    let buf = read_exact_vec(reader, len)?;
+    String::from_utf8(buf).map_err(|e| FirmwareError::Parse(format!("Invalid UTF-8 in embedded part name: {}", e)))
+}
+
+pub(crate) fn parse_header<R: Read>(reader: &mut R) -> Result<FirmwareHeader, FirmwareError> {
+    let magic = read_exact_vec(reader, 4)?;
+    if magic != MAGIC {
+        return Err(FirmwareError::Parse(format!(
+            "Bad embedded header magic: expected {:?}, got {:?}",
+            MAGIC, magic
+        )));
+    }
+
+    let version = read_u32(reader)?;
+    if version != FORMAT_VERSION {
+        return Err(FirmwareError::Parse(format!(
+            "Unsupported embedded header version: {}",
+            version
+        )));
+    }
+
+    let declared_size = read_u64(reader)?;
+    let part_count = read_u32(reader)?;
+
+    // `R: Read` alone gives no way to know how many bytes are actually left, so bound
+    // part_count against a generous sanity cap instead of trusting it for with_capacity.
+    const MAX_PARTS: u64 = 1_000_000;
+    crate::check_claimed_len(part_count as u64, MAX_PARTS, "embedded header part count")?;
+
+    let parts = if part_count == 0 {
+        None
+    } else {
+        let mut parts = Vec::with_capacity(part_count as usize);
+        for _ in 0..part_count {
+            let name = read_string(reader)?;
+            let offset = read_u64(reader)?;
+            let size = read_u64(reader)?;
+            parts.push(FirmwarePart {
+                name,
+                offset,
+                size,
+                padding_byte: 0xFF,
+                padding_mode: crate::padding::PaddingMode::Constant(0xFF),
+                use_custom_padding: false,
+                has_explicit_size: true,
+            });
+        }
+        Some(parts)
+    };
+
+    Ok(FirmwareHeader {
+        version,
+        declared_size,
+        parts,
+    })
+}
+
+pub(crate) fn warn_on_discrepancies(header: &FirmwareHeader, config_parts: &[FirmwarePart]) {
+    let computed_size = config_parts.iter().map(|p| p.offset + p.size).max().unwrap_or(0);
+    if header.declared_size != 0 && header.declared_size != computed_size {
+        println!(
+            "Warning: embedded header declares size 0x{:x}, config computes 0x{:x}",
+            header.declared_size, computed_size
+        );
+    }
+
+    let Some(header_parts) = &header.parts else {
+        return;
+    };
+
+    for header_part in header_parts {
+        match config_parts.iter().find(|p| p.name == header_part.name) {
+            Some(config_part) => {
+                if config_part.offset != header_part.offset || config_part.size != header_part.size {
+                    println!(
+                        "Warning: part '{}' differs between embedded header (offset=0x{:x}, size=0x{:x}) and config (offset=0x{:x}, size=0x{:x})",
+                        header_part.name,
+                        header_part.offset,
+                        header_part.size,
+                        config_part.offset,
+                        config_part.size
+                    );
+                }
+            }
+            None => {
+                println!(
+                    "Warning: part '{}' is declared in the embedded header but not in the config file",
+                    header_part.name
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_header(declared_size: u64, parts: &[(&str, u64, u64)]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        buf.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        buf.extend_from_slice(&declared_size.to_le_bytes());
+        buf.extend_from_slice(&(parts.len() as u32).to_le_bytes());
+        for (name, offset, size) in parts {
+            buf.extend_from_slice(&(name.len() as u64).to_le_bytes());
+            buf.extend_from_slice(name.as_bytes());
+            buf.extend_from_slice(&offset.to_le_bytes());
+            buf.extend_from_slice(&size.to_le_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn parse_header_round_trip() {
+        let encoded = encode_header(0x100, &[("part_a", 0x0, 0x80), ("part_b", 0x80, 0x80)]);
+        let header = parse_header(&mut encoded.as_slice()).unwrap();
+
+        assert_eq!(header.version, FORMAT_VERSION);
+        assert_eq!(header.declared_size, 0x100);
+        let parts = header.parts.unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[0].name, "part_a");
+        assert_eq!(parts[0].offset, 0x0);
+        assert_eq!(parts[0].size, 0x80);
+        assert_eq!(parts[1].name, "part_b");
+        assert_eq!(parts[1].offset, 0x80);
+        assert_eq!(parts[1].size, 0x80);
+    }
+}
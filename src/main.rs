@@ -4,20 +4,31 @@ use std::path::Path;
 use std::str::FromStr;
 use sha2::{Sha256, Digest};
 
+mod container;
+mod header;
+mod manifest;
+mod padding;
+mod sparse;
+mod volume;
+
+use container::Codec;
+use padding::PaddingMode;
+
 #[derive(Debug)]
 #[allow(dead_code)]
-struct FirmwarePart {
-    name: String,
-    offset: u64,
-    size: u64,
-    padding_byte: u8,
-    use_custom_padding: bool,
-    has_explicit_size: bool,
+pub(crate) struct FirmwarePart {
+    pub(crate) name: String,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    pub(crate) padding_byte: u8,
+    pub(crate) padding_mode: PaddingMode,
+    pub(crate) use_custom_padding: bool,
+    pub(crate) has_explicit_size: bool,
 }
 
 #[derive(Debug)]
 #[allow(dead_code)]
-enum FirmwareError {
+pub(crate) enum FirmwareError {
     Io(io::Error),
     Parse(String),
     Config(String),
@@ -29,7 +40,7 @@ impl From<io::Error> for FirmwareError {
     }
 }
 
-fn parse_number(s: &str) -> Result<u64, FirmwareError> {
+pub(crate) fn parse_number(s: &str) -> Result<u64, FirmwareError> {
     let s = s.trim();
     if s.is_empty() {
         return Ok(0);
@@ -47,13 +58,31 @@ fn get_file_size(path: &Path) -> io::Result<u64> {
     Ok(fs::metadata(path)?.len())
 }
 
+/// Rejects a length claimed by an untrusted header before it's used to size
+/// an allocation, instead of aborting the process on a corrupted file.
+pub(crate) fn check_claimed_len(claimed: u64, available: u64, what: &str) -> Result<(), FirmwareError> {
+    if claimed > available {
+        Err(FirmwareError::Parse(format!(
+            "Claimed {} ({}) exceeds available data ({})",
+            what, claimed, available
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+#[allow(clippy::ptr_arg)]
 fn calculate_sizes(
     parts: &mut Vec<FirmwarePart>,
     is_unpack: bool,
     input_file: &Path,
 ) -> Result<(), FirmwareError> {
     let total_size = if is_unpack {
-        get_file_size(input_file)?
+        if volume::is_split(input_file) {
+            volume::logical_size(input_file)?
+        } else {
+            get_file_size(input_file)?
+        }
     } else {
         parts
             .iter()
@@ -82,7 +111,7 @@ fn calculate_sizes(
     Ok(())
 }
 
-fn read_config(
+pub(crate) fn read_config(
     config_path: &Path,
     firmware_path: &Path,
     is_unpack: bool,
@@ -112,10 +141,14 @@ fn read_config(
             (0, false)
         };
 
-        let padding_byte = if fields.len() > 3 {
-            parse_number(fields[3])? as u8
+        let padding_mode = if fields.len() > 3 && !fields[3].trim().is_empty() {
+            PaddingMode::parse(fields[3], offset)?
         } else {
-            0xFF
+            PaddingMode::Constant(0xFF)
+        };
+        let padding_byte = match padding_mode {
+            PaddingMode::Constant(byte) => byte,
+            PaddingMode::Lfg { .. } => 0xFF,
         };
 
         parts.push(FirmwarePart {
@@ -123,6 +156,7 @@ fn read_config(
             offset,
             size,
             padding_byte,
+            padding_mode,
             use_custom_padding: fields.len() > 3,
             has_explicit_size,
         });
@@ -133,23 +167,25 @@ fn read_config(
     println!("Firmware parts:");
     for part in &parts {
         println!(
-            "{}: offset=0x{:x}, size=0x{:x}{}, padding=0x{:02X}",
+            "{}: offset=0x{:x}, size=0x{:x}{}, padding={}",
             part.name,
             part.offset,
             part.size,
             if part.has_explicit_size { "" } else { " (auto)" },
-            part.padding_byte
+            part.padding_mode.describe()
         );
     }
 
     Ok(parts)
 }
 
-fn unpack_firmware(
+pub(crate) fn unpack_firmware(
     firmware_path: &Path,
     parts: &[FirmwarePart],
 ) -> Result<(), FirmwareError> {
-    let mut firmware = File::open(firmware_path)?;
+    // Transparently follows a split volume set (`firmware_path.000`, `.001`, ...)
+    // in place of a single file.
+    let mut firmware = volume::open_reader(firmware_path)?;
     let mut buffer = vec![0u8; 4096];
 
     for part in parts {
@@ -182,7 +218,7 @@ fn unpack_firmware(
     Ok(())
 }
 
-fn pack_firmware(
+pub(crate) fn pack_firmware(
     firmware_path: &Path,
     parts: &[FirmwarePart],
 ) -> Result<(), FirmwareError> {
@@ -194,8 +230,33 @@ fn pack_firmware(
         .unwrap_or(0);
 
     firmware.set_len(max_size)?;
+    pack_firmware_to(&mut firmware, parts, max_size)
+}
+
+/// Packs `parts` into a set of fixed-size split volumes named
+/// `firmware_path.000`, `.001`, ... instead of a single file.
+pub(crate) fn pack_firmware_split(
+    firmware_path: &Path,
+    parts: &[FirmwarePart],
+    volume_size: u64,
+) -> Result<(), FirmwareError> {
+    let max_size = parts
+        .iter()
+        .map(|p| p.offset + p.size)
+        .max()
+        .unwrap_or(0);
+
+    let mut firmware = volume::VolumeWriter::create(firmware_path, volume_size)?;
+    pack_firmware_to(&mut firmware, parts, max_size)
+}
+
+fn pack_firmware_to<W: Write + Seek>(
+    firmware: &mut W,
+    parts: &[FirmwarePart],
+    max_size: u64,
+) -> Result<(), FirmwareError> {
     firmware.seek(SeekFrom::Start(0))?;
-    
+
     let fill_buffer = vec![0xFF_u8; 4096];
     let mut remaining = max_size;
     while remaining > 0 {
@@ -234,22 +295,32 @@ fn pack_firmware(
 
             if written < part.size {
                 let padding_size = part.size - written;
-                let padding_buffer = vec![part.padding_byte; 4096];
+                let mut padding_buffer = vec![0u8; 4096];
+                let mut lfg = match part.padding_mode {
+                    PaddingMode::Constant(byte) => {
+                        padding_buffer.fill(byte);
+                        None
+                    }
+                    PaddingMode::Lfg { seed } => Some(padding::Lfg::new(seed)),
+                };
                 let mut remaining_padding = padding_size;
 
                 while remaining_padding > 0 {
                     let to_write = remaining_padding.min(padding_buffer.len() as u64) as usize;
+                    if let Some(lfg) = lfg.as_mut() {
+                        lfg.fill(&mut padding_buffer[..to_write]);
+                    }
                     firmware.write_all(&padding_buffer[..to_write])?;
                     hasher.update(&padding_buffer[..to_write]);
                     remaining_padding -= to_write as u64;
                 }
 
                 println!(
-                    "Wrote {}: {} bytes (padded {} bytes with 0x{:02X}), SHA256: {:x}",
+                    "Wrote {}: {} bytes (padded {} bytes with {}), SHA256: {:x}",
                     part.name,
                     written,
                     padding_size,
-                    part.padding_byte,
+                    part.padding_mode.describe(),
                     hasher.finalize()
                 );
             } else {
@@ -269,31 +340,165 @@ fn pack_firmware(
 }
 
 fn print_usage() {
-    println!("Usage: firmware_tool [unpack|pack] <firmware_file> <config_file>");
+    println!("Usage: firmware_tool [unpack|pack] <firmware_file> <config_file> [--compress <codec>] [--sparse [block_size]] [--split <size>] [--manifest <path>] [--use-header]");
+    println!("       firmware_tool verify <firmware_file> <manifest_file>");
+    println!("  --compress <codec>   pack: write a compressed container (none, zstd, lzma, bzip2)");
+    println!("  --sparse [block_size] pack: write a sparse block map, skipping all-padding blocks");
+    println!("                        (block_size defaults to 0x8000)");
+    println!("  --split <size>       pack: split the image into fixed-size volumes, e.g. `--split 2G`");
+    println!("                        (firmware_file.000, .001, ...)");
+    println!("                       unpack/verify: a split volume set is auto-detected, flags are ignored");
+    println!("  --manifest <path>    write a checksum manifest (.json or .csv) after pack/unpack");
+    println!("  --use-header         unpack: derive parts from the image's embedded part table instead of");
+    println!("                        the config file (the config's parts are used if the table is empty),");
+    println!("                        warning when the declared size or a part's offset/size differs from");
+    println!("                        the config or a header part is missing from it; parts found only in");
+    println!("                        the config file are not reported");
     println!("Config file format:");
-    println!("name, offset [, size] [, padding_byte]");
+    println!("name, offset [, size] [, padding_byte | lfg | prng:<seed>]");
     println!("Example:");
     println!("header, 0x0, 0x40");
     println!("kernel, 0x40, , 0x00     # size will be auto-calculated");
     println!("rootfs, 0x200040         # size from input file or next offset");
+    println!("extra, 0x300000, 0x1000, lfg   # gap filled with a deterministic PRNG stream");
 }
 
 fn main() -> Result<(), FirmwareError> {
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() != 4 {
+    let all_args: Vec<String> = std::env::args().collect();
+
+    let mut positional = Vec::new();
+    let mut compress: Option<Codec> = None;
+    let mut sparse_block_size: Option<u64> = None;
+    let mut split_volume_size: Option<u64> = None;
+    let mut manifest_path: Option<String> = None;
+    let mut use_header = false;
+    let mut i = 1;
+    while i < all_args.len() {
+        match all_args[i].as_str() {
+            "--compress" => {
+                let name = all_args.get(i + 1).ok_or_else(|| {
+                    FirmwareError::Parse("--compress requires a codec name".into())
+                })?;
+                compress = Some(Codec::from_name(name)?);
+                i += 2;
+            }
+            "--sparse" => {
+                match all_args.get(i + 1) {
+                    Some(next) if !next.starts_with("--") => {
+                        sparse_block_size = Some(parse_number(next)?);
+                        i += 2;
+                    }
+                    _ => {
+                        sparse_block_size = Some(sparse::DEFAULT_BLOCK_SIZE);
+                        i += 1;
+                    }
+                }
+            }
+            "--split" => {
+                let size = all_args.get(i + 1).ok_or_else(|| {
+                    FirmwareError::Parse("--split requires a volume size".into())
+                })?;
+                split_volume_size = Some(volume::parse_size(size)?);
+                i += 2;
+            }
+            "--manifest" => {
+                let path = all_args.get(i + 1).ok_or_else(|| {
+                    FirmwareError::Parse("--manifest requires a file path".into())
+                })?;
+                manifest_path = Some(path.clone());
+                i += 2;
+            }
+            "--use-header" => {
+                use_header = true;
+                i += 1;
+            }
+            other => {
+                positional.push(other.to_string());
+                i += 1;
+            }
+        }
+    }
+
+    if positional.len() != 3 {
         print_usage();
         return Ok(());
     }
 
-    let is_unpack = args[1] == "unpack";
-    let firmware_path = Path::new(&args[2]);
-    let config_path = Path::new(&args[3]);
+    let is_unpack = positional[0] == "unpack";
+    let firmware_path = Path::new(&positional[1]);
+    let config_path = Path::new(&positional[2]);
 
-    let parts = read_config(config_path, firmware_path, is_unpack)?;
+    match positional[0].as_str() {
+        "unpack" => {
+            let parts = if use_header {
+                let config_parts = read_config(config_path, firmware_path, is_unpack)?;
+                let mut reader = volume::open_reader(firmware_path)?;
+                let firmware_header = header::parse_header(&mut reader)?;
+                println!(
+                    "Embedded header: format version {}, declared size 0x{:x}",
+                    firmware_header.version, firmware_header.declared_size
+                );
+                header::warn_on_discrepancies(&firmware_header, &config_parts);
+                let parts = firmware_header.parts.unwrap_or(config_parts);
+                unpack_firmware(firmware_path, &parts)?;
+                Some(parts)
+            } else if volume::is_split(firmware_path) {
+                let parts = read_config(config_path, firmware_path, is_unpack)?;
+                unpack_firmware(firmware_path, &parts)?;
+                Some(parts)
+            } else if container::is_compressed_container(firmware_path)? {
+                container::unpack_compressed(firmware_path)?;
+                None
+            } else if sparse::is_sparse_image(firmware_path)? {
+                sparse::unpack_sparse(firmware_path, config_path)?;
+                None
+            } else {
+                let parts = read_config(config_path, firmware_path, is_unpack)?;
+                unpack_firmware(firmware_path, &parts)?;
+                Some(parts)
+            };
+
+            if let Some(manifest_path) = manifest_path {
+                let parts = parts.ok_or_else(|| {
+                    FirmwareError::Config("--manifest is only supported for the flat or split unpack format".into())
+                })?;
+                let manifest = manifest::build_manifest(firmware_path, &parts)?;
+                manifest::write_manifest(Path::new(&manifest_path), &manifest)?;
+            }
+        }
+        "pack" => {
+            let parts = read_config(config_path, firmware_path, is_unpack)?;
+
+            let exclusive_flags = [compress.is_some(), sparse_block_size.is_some(), split_volume_size.is_some()]
+                .iter()
+                .filter(|&&set| set)
+                .count();
+            if exclusive_flags > 1 {
+                return Err(FirmwareError::Config(
+                    "--compress, --sparse, and --split are mutually exclusive".into(),
+                ));
+            }
 
-    match args[1].as_str() {
-        "unpack" => unpack_firmware(firmware_path, &parts)?,
-        "pack" => pack_firmware(firmware_path, &parts)?,
+            match (compress, sparse_block_size, split_volume_size) {
+                (Some(codec), _, _) => container::pack_compressed(firmware_path, &parts, codec)?,
+                (None, Some(block_size), _) => sparse::pack_sparse(firmware_path, &parts, block_size)?,
+                (None, None, Some(volume_size)) => pack_firmware_split(firmware_path, &parts, volume_size)?,
+                (None, None, None) => pack_firmware(firmware_path, &parts)?,
+            }
+
+            if let Some(manifest_path) = manifest_path {
+                if compress.is_some() || sparse_block_size.is_some() {
+                    return Err(FirmwareError::Config(
+                        "--manifest is only supported for the flat or split pack format".into(),
+                    ));
+                }
+                let manifest = manifest::build_manifest(firmware_path, &parts)?;
+                manifest::write_manifest(Path::new(&manifest_path), &manifest)?;
+            }
+        }
+        "verify" => {
+            manifest::verify_firmware(firmware_path, config_path)?;
+        }
         _ => {
             print_usage();
             return Ok(());
@@ -0,0 +1,313 @@
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use crc32fast::Hasher as Crc32Hasher;
+use sha2::{Digest, Sha256};
+
+use crate::{FirmwareError, FirmwarePart};
+
+#[derive(Debug, Clone)]
+pub(crate) struct PartRecord {
+    pub(crate) name: String,
+    pub(crate) offset: u64,
+    pub(crate) size: u64,
+    pub(crate) padding_byte: u8,
+    pub(crate) padding_mode: String,
+    pub(crate) sha256: String,
+    pub(crate) crc32: u32,
+}
+
+pub(crate) struct Manifest {
+    pub(crate) image_sha256: String,
+    pub(crate) parts: Vec<PartRecord>,
+}
+
+pub(crate) fn build_manifest(
+    firmware_path: &Path,
+    parts: &[FirmwarePart],
+) -> Result<Manifest, FirmwareError> {
+    let mut firmware = crate::volume::open_reader(firmware_path)?;
+    let mut buffer = vec![0u8; 4096];
+
+    let mut records = Vec::with_capacity(parts.len());
+    for part in parts {
+        firmware.seek(SeekFrom::Start(part.offset))?;
+        let mut remaining = part.size;
+        let mut sha = Sha256::new();
+        let mut crc = Crc32Hasher::new();
+
+        while remaining > 0 {
+            let to_read = remaining.min(buffer.len() as u64) as usize;
+            let bytes_read = firmware.read(&mut buffer[..to_read])?;
+            if bytes_read == 0 {
+                break;
+            }
+            sha.update(&buffer[..bytes_read]);
+            crc.update(&buffer[..bytes_read]);
+            remaining -= bytes_read as u64;
+        }
+
+        records.push(PartRecord {
+            name: part.name.clone(),
+            offset: part.offset,
+            size: part.size,
+            padding_byte: part.padding_byte,
+            padding_mode: part.padding_mode.describe(),
+            sha256: format!("{:x}", sha.finalize()),
+            crc32: crc.finalize(),
+        });
+    }
+
+    let image_sha256 = hash_file(firmware_path)?;
+
+    Ok(Manifest {
+        image_sha256,
+        parts: records,
+    })
+}
+
+fn hash_file(path: &Path) -> Result<String, FirmwareError> {
+    let mut file = crate::volume::open_reader(path)?;
+    let mut buffer = vec![0u8; 4096];
+    let mut hasher = Sha256::new();
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+pub(crate) fn write_manifest(path: &Path, manifest: &Manifest) -> Result<(), FirmwareError> {
+    let is_csv = path
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("csv"))
+        .unwrap_or(false);
+
+    let content = if is_csv {
+        write_csv(manifest)
+    } else {
+        write_json(manifest)
+    };
+
+    fs::write(path, content).map_err(FirmwareError::Io)
+}
+
+fn write_json(manifest: &Manifest) -> String {
+    let mut out = String::new();
+    out.push_str("{\n");
+    out.push_str(&format!("  \"image_sha256\": \"{}\",\n", manifest.image_sha256));
+    out.push_str("  \"parts\": [\n");
+    for (i, r) in manifest.parts.iter().enumerate() {
+        out.push_str(&format!(
+            "    {{\"name\": \"{}\", \"offset\": {}, \"size\": {}, \"padding_byte\": {}, \"padding_mode\": \"{}\", \"sha256\": \"{}\", \"crc32\": {}}}{}\n",
+            r.name,
+            r.offset,
+            r.size,
+            r.padding_byte,
+            r.padding_mode,
+            r.sha256,
+            r.crc32,
+            if i + 1 < manifest.parts.len() { "," } else { "" }
+        ));
+    }
+    out.push_str("  ]\n}\n");
+    out
+}
+
+fn write_csv(manifest: &Manifest) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("# image_sha256,{}\n", manifest.image_sha256));
+    out.push_str("name,offset,size,padding_byte,padding_mode,sha256,crc32\n");
+    for r in &manifest.parts {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            r.name, r.offset, r.size, r.padding_byte, r.padding_mode, r.sha256, r.crc32
+        ));
+    }
+    out
+}
+
+pub(crate) fn read_manifest(path: &Path) -> Result<Manifest, FirmwareError> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| FirmwareError::Config(format!("Failed to read manifest: {}", e)))?;
+
+    if content.trim_start().starts_with('{') {
+        read_json(&content)
+    } else {
+        read_csv(&content)
+    }
+}
+
+fn field(line: &str, key: &str) -> Result<String, FirmwareError> {
+    let needle = format!("\"{}\":", key);
+    let start = line
+        .find(&needle)
+        .ok_or_else(|| FirmwareError::Parse(format!("Manifest line missing field '{}'", key)))?
+        + needle.len();
+    let rest = line[start..].trim_start();
+    if let Some(stripped) = rest.strip_prefix('"') {
+        let end = stripped
+            .find('"')
+            .ok_or_else(|| FirmwareError::Parse("Unterminated string in manifest".into()))?;
+        Ok(stripped[..end].to_string())
+    } else {
+        let end = rest
+            .find([',', '}'])
+            .ok_or_else(|| FirmwareError::Parse("Unterminated value in manifest".into()))?;
+        Ok(rest[..end].trim().to_string())
+    }
+}
+
+fn read_json(content: &str) -> Result<Manifest, FirmwareError> {
+    let image_sha256 = field(content, "image_sha256")?;
+
+    let mut parts = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if !(line.starts_with('{') && line.contains("\"name\":")) {
+            continue;
+        }
+        parts.push(PartRecord {
+            name: field(line, "name")?,
+            offset: crate::parse_number(&field(line, "offset")?)?,
+            size: crate::parse_number(&field(line, "size")?)?,
+            padding_byte: crate::parse_number(&field(line, "padding_byte")?)? as u8,
+            padding_mode: field(line, "padding_mode")?,
+            sha256: field(line, "sha256")?,
+            crc32: crate::parse_number(&field(line, "crc32")?)? as u32,
+        });
+    }
+
+    Ok(Manifest { image_sha256, parts })
+}
+
+fn read_csv(content: &str) -> Result<Manifest, FirmwareError> {
+    let mut image_sha256 = String::new();
+    let mut parts = Vec::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line == "name,offset,size,padding_byte,padding_mode,sha256,crc32" {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("# image_sha256,") {
+            image_sha256 = rest.trim().to_string();
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() != 7 {
+            return Err(FirmwareError::Parse(format!("Malformed manifest row: {}", line)));
+        }
+        parts.push(PartRecord {
+            name: fields[0].to_string(),
+            offset: crate::parse_number(fields[1])?,
+            size: crate::parse_number(fields[2])?,
+            padding_byte: crate::parse_number(fields[3])? as u8,
+            padding_mode: fields[4].to_string(),
+            sha256: fields[5].to_string(),
+            crc32: crate::parse_number(fields[6])? as u32,
+        });
+    }
+
+    Ok(Manifest { image_sha256, parts })
+}
+
+pub(crate) fn verify_firmware(firmware_path: &Path, manifest_path: &Path) -> Result<(), FirmwareError> {
+    let manifest = read_manifest(manifest_path)?;
+
+    let actual_image_sha256 = hash_file(firmware_path)?;
+    let mut mismatches = 0;
+
+    if actual_image_sha256 != manifest.image_sha256 {
+        println!(
+            "MISMATCH image: expected SHA256 {}, got {}",
+            manifest.image_sha256, actual_image_sha256
+        );
+        mismatches += 1;
+    }
+
+    let mut firmware = crate::volume::open_reader(firmware_path)?;
+    let mut buffer = vec![0u8; 4096];
+
+    for record in &manifest.parts {
+        firmware.seek(SeekFrom::Start(record.offset))?;
+        let mut remaining = record.size;
+        let mut sha = Sha256::new();
+        let mut crc = Crc32Hasher::new();
+
+        while remaining > 0 {
+            let to_read = remaining.min(buffer.len() as u64) as usize;
+            let bytes_read = firmware.read(&mut buffer[..to_read])?;
+            if bytes_read == 0 {
+                break;
+            }
+            sha.update(&buffer[..bytes_read]);
+            crc.update(&buffer[..bytes_read]);
+            remaining -= bytes_read as u64;
+        }
+
+        let actual_sha256 = format!("{:x}", sha.finalize());
+        let actual_crc32 = crc.finalize();
+
+        if actual_sha256 == record.sha256 && actual_crc32 == record.crc32 {
+            println!("OK {}: SHA256 {}", record.name, actual_sha256);
+        } else {
+            println!(
+                "MISMATCH {}: expected SHA256 {} CRC32 {:08x}, got SHA256 {} CRC32 {:08x}",
+                record.name, record.sha256, record.crc32, actual_sha256, actual_crc32
+            );
+            mismatches += 1;
+        }
+    }
+
+    if mismatches > 0 {
+        Err(FirmwareError::Parse(format!(
+            "{} mismatch(es) found during verification",
+            mismatches
+        )))
+    } else {
+        println!("All parts verified OK");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padding::PaddingMode;
+
+    #[test]
+    fn build_write_verify_round_trip() {
+        fs::write("manifest_rt_part.bin", b"manifest round trip data").unwrap();
+
+        let parts = [FirmwarePart {
+            name: "manifest_rt_part".to_string(),
+            offset: 0,
+            size: 24,
+            padding_byte: 0xFF,
+            padding_mode: PaddingMode::Constant(0xFF),
+            use_custom_padding: false,
+            has_explicit_size: true,
+        }];
+
+        let firmware_path = Path::new("manifest_rt.bin");
+        crate::pack_firmware(firmware_path, &parts).unwrap();
+
+        let manifest = build_manifest(firmware_path, &parts).unwrap();
+        let manifest_path = Path::new("manifest_rt.json");
+        write_manifest(manifest_path, &manifest).unwrap();
+
+        assert!(verify_firmware(firmware_path, manifest_path).is_ok());
+
+        fs::write("manifest_rt.bin", [0u8; 24]).unwrap();
+        assert!(verify_firmware(firmware_path, manifest_path).is_err());
+
+        fs::remove_file(firmware_path).unwrap();
+        fs::remove_file(manifest_path).unwrap();
+        fs::remove_file("manifest_rt_part.bin").unwrap();
+    }
+}
@@ -0,0 +1,98 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PaddingMode {
+    Constant(u8),
+    Lfg { seed: u64 },
+}
+
+impl PaddingMode {
+    pub(crate) fn parse(s: &str, part_offset: u64) -> Result<Self, crate::FirmwareError> {
+        let trimmed = s.trim();
+        if trimmed.eq_ignore_ascii_case("lfg") {
+            Ok(PaddingMode::Lfg { seed: part_offset })
+        } else if let Some(seed) = trimmed
+            .to_ascii_lowercase()
+            .strip_prefix("prng:")
+            .map(|s| s.to_string())
+        {
+            Ok(PaddingMode::Lfg {
+                seed: crate::parse_number(&seed)?,
+            })
+        } else {
+            Ok(PaddingMode::Constant(crate::parse_number(trimmed)? as u8))
+        }
+    }
+
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            PaddingMode::Constant(byte) => format!("constant:0x{:02x}", byte),
+            PaddingMode::Lfg { seed } => format!("lfg:seed=0x{:x}", seed),
+        }
+    }
+}
+
+/// `w[i] = w[i-17] xor w[i-5]`, streamed out as little-endian bytes.
+pub(crate) struct Lfg {
+    ring: [u32; 17],
+    pos: usize,
+}
+
+impl Lfg {
+    pub(crate) fn new(seed: u64) -> Self {
+        let mut x = seed ^ 0x9E37_79B9_7F4A_7C15;
+        let mut ring = [0u32; 17];
+        for slot in ring.iter_mut() {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            *slot = (x ^ (x >> 32)) as u32;
+        }
+        Lfg { ring, pos: 0 }
+    }
+
+    fn next_u32(&mut self) -> u32 {
+        let lag17 = self.ring[self.pos];
+        let lag5 = self.ring[(self.pos + 17 - 5) % 17];
+        let word = lag17 ^ lag5;
+        self.ring[self.pos] = word;
+        self.pos = (self.pos + 1) % 17;
+        word
+    }
+
+    pub(crate) fn fill(&mut self, buf: &mut [u8]) {
+        let mut written = 0;
+        while written < buf.len() {
+            let word = self.next_u32().to_le_bytes();
+            let n = (buf.len() - written).min(4);
+            buf[written..written + n].copy_from_slice(&word[..n]);
+            written += n;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_modes_and_deterministic_stream() {
+        assert_eq!(PaddingMode::parse("0x7F", 0x10).unwrap(), PaddingMode::Constant(0x7F));
+        assert_eq!(PaddingMode::parse("lfg", 0x10).unwrap(), PaddingMode::Lfg { seed: 0x10 });
+        assert_eq!(
+            PaddingMode::parse("prng:0xABCD", 0x10).unwrap(),
+            PaddingMode::Lfg { seed: 0xABCD }
+        );
+
+        let mut one_shot = Lfg::new(42);
+        let mut full = vec![0u8; 32];
+        one_shot.fill(&mut full);
+
+        let mut split = Lfg::new(42);
+        let mut first_half = vec![0u8; 16];
+        let mut second_half = vec![0u8; 16];
+        split.fill(&mut first_half);
+        split.fill(&mut second_half);
+
+        assert_eq!(&full[..16], &first_half[..]);
+        assert_eq!(&full[16..], &second_half[..]);
+    }
+}
@@ -0,0 +1,204 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{FirmwareError, FirmwarePart};
+
+pub const MAGIC: &[u8; 4] = b"RNSB";
+pub const FORMAT_VERSION: u32 = 1;
+pub const DEFAULT_BLOCK_SIZE: u64 = 0x8000;
+
+struct BlockEntry {
+    present: bool,
+    fill_byte: u8,
+}
+
+pub fn is_sparse_image(firmware_path: &Path) -> io::Result<bool> {
+    let mut file = File::open(firmware_path)?;
+    let mut magic = [0u8; 4];
+    match file.read_exact(&mut magic) {
+        Ok(()) => Ok(&magic == MAGIC),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn assembling_path(firmware_path: &Path) -> PathBuf {
+    let mut name = firmware_path.as_os_str().to_owned();
+    name.push(".assembling");
+    PathBuf::from(name)
+}
+
+pub fn pack_sparse(
+    firmware_path: &Path,
+    parts: &[FirmwarePart],
+    block_size: u64,
+) -> Result<(), FirmwareError> {
+    if block_size == 0 {
+        return Err(FirmwareError::Config("--sparse block size must be nonzero".into()));
+    }
+
+    let scratch_path = assembling_path(firmware_path);
+    crate::pack_firmware(&scratch_path, parts)?;
+
+    let total_size = fs::metadata(&scratch_path)?.len();
+    let block_count = total_size.div_ceil(block_size);
+
+    let mut scratch = File::open(&scratch_path)?;
+    let mut entries = Vec::with_capacity(block_count as usize);
+    let mut stored_blocks = Vec::new();
+    let mut block = vec![0u8; block_size as usize];
+    let mut remaining = total_size;
+
+    for _ in 0..block_count {
+        let this_block = block_size.min(remaining) as usize;
+        scratch.read_exact(&mut block[..this_block])?;
+        remaining -= this_block as u64;
+        let slice = &block[..this_block];
+
+        let fill_byte = slice.first().copied().unwrap_or(0);
+        let is_uniform = !slice.is_empty() && slice.iter().all(|&b| b == fill_byte);
+
+        if is_uniform {
+            entries.push(BlockEntry {
+                present: false,
+                fill_byte,
+            });
+        } else {
+            entries.push(BlockEntry {
+                present: true,
+                fill_byte: 0,
+            });
+            stored_blocks.push(slice.to_vec());
+        }
+    }
+
+    drop(scratch);
+    fs::remove_file(&scratch_path)?;
+
+    let mut firmware = File::create(firmware_path)?;
+    firmware.write_all(MAGIC)?;
+    firmware.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    firmware.write_all(&block_size.to_le_bytes())?;
+    firmware.write_all(&total_size.to_le_bytes())?;
+    firmware.write_all(&block_count.to_le_bytes())?;
+    for entry in &entries {
+        firmware.write_all(&[entry.present as u8, entry.fill_byte])?;
+    }
+    for stored in &stored_blocks {
+        firmware.write_all(stored)?;
+    }
+
+    println!(
+        "Sparse-packed {} in {} blocks of 0x{:x}: {} stored, {} absent ({} bytes of block data stored)",
+        firmware_path.display(),
+        block_count,
+        block_size,
+        stored_blocks.len(),
+        entries.len() - stored_blocks.len(),
+        stored_blocks.iter().map(|b| b.len() as u64).sum::<u64>()
+    );
+
+    Ok(())
+}
+
+pub fn unpack_sparse(firmware_path: &Path, config_path: &Path) -> Result<(), FirmwareError> {
+    let mut firmware = File::open(firmware_path)?;
+
+    let mut magic = [0u8; 4];
+    firmware.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(FirmwareError::Parse("Bad sparse container magic".into()));
+    }
+
+    let mut u32_buf = [0u8; 4];
+    firmware.read_exact(&mut u32_buf)?;
+    let version = u32::from_le_bytes(u32_buf);
+    if version != FORMAT_VERSION {
+        return Err(FirmwareError::Parse(format!(
+            "Unsupported sparse container version: {}",
+            version
+        )));
+    }
+
+    let mut u64_buf = [0u8; 8];
+    firmware.read_exact(&mut u64_buf)?;
+    let block_size = u64::from_le_bytes(u64_buf);
+    firmware.read_exact(&mut u64_buf)?;
+    let total_size = u64::from_le_bytes(u64_buf);
+    firmware.read_exact(&mut u64_buf)?;
+    let block_count = u64::from_le_bytes(u64_buf);
+
+    let available = firmware.metadata()?.len().saturating_sub(firmware.stream_position()?);
+    const ENTRY_SIZE: u64 = 2;
+    crate::check_claimed_len(block_count.saturating_mul(ENTRY_SIZE), available, "sparse block index")?;
+
+    let mut entries = Vec::with_capacity(block_count as usize);
+    for _ in 0..block_count {
+        let mut flag_byte = [0u8; 2];
+        firmware.read_exact(&mut flag_byte)?;
+        entries.push(BlockEntry {
+            present: flag_byte[0] != 0,
+            fill_byte: flag_byte[1],
+        });
+    }
+
+    let scratch_path = assembling_path(firmware_path);
+    let mut scratch = File::create(&scratch_path)?;
+    let mut remaining = total_size;
+    let mut block = vec![0u8; block_size as usize];
+
+    for entry in &entries {
+        let this_block = block_size.min(remaining) as usize;
+        if entry.present {
+            firmware.read_exact(&mut block[..this_block])?;
+            scratch.write_all(&block[..this_block])?;
+        } else {
+            let fill = vec![entry.fill_byte; this_block];
+            scratch.write_all(&fill)?;
+        }
+        remaining -= this_block as u64;
+    }
+    drop(scratch);
+
+    let result = crate::read_config(config_path, &scratch_path, true)
+        .and_then(|parts| crate::unpack_firmware(&scratch_path, &parts));
+    fs::remove_file(&scratch_path)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::padding::PaddingMode;
+
+    #[test]
+    fn pack_unpack_round_trip() {
+        let data = vec![b'A'; 40];
+        fs::write("sparse_rt_part.bin", &data).unwrap();
+        fs::write("sparse_rt.cfg", "sparse_rt_part,0x0,0x40\n").unwrap();
+
+        let parts = [FirmwarePart {
+            name: "sparse_rt_part".to_string(),
+            offset: 0,
+            size: 0x40,
+            padding_byte: 0xFF,
+            padding_mode: PaddingMode::Constant(0xFF),
+            use_custom_padding: false,
+            has_explicit_size: true,
+        }];
+
+        let firmware_path = Path::new("sparse_rt.bin");
+        pack_sparse(firmware_path, &parts, 0x10).unwrap();
+        assert!(is_sparse_image(firmware_path).unwrap());
+
+        unpack_sparse(firmware_path, Path::new("sparse_rt.cfg")).unwrap();
+        let mut expected = data;
+        expected.extend(std::iter::repeat_n(0xFFu8, 24));
+        assert_eq!(fs::read("sparse_rt_part.bin").unwrap(), expected);
+
+        fs::remove_file(firmware_path).unwrap();
+        fs::remove_file("sparse_rt_part.bin").unwrap();
+        fs::remove_file("sparse_rt.cfg").unwrap();
+    }
+}
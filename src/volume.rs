@@ -0,0 +1,236 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+pub(crate) trait ReadSeek: Read + Seek {}
+impl<T: Read + Seek> ReadSeek for T {}
+
+fn volume_path(base: &Path, index: u64) -> PathBuf {
+    let mut name = base.as_os_str().to_owned();
+    name.push(format!(".{:03}", index));
+    PathBuf::from(name)
+}
+
+pub(crate) fn is_split(base: &Path) -> bool {
+    volume_path(base, 0).is_file()
+}
+
+pub(crate) fn parse_size(s: &str) -> Result<u64, crate::FirmwareError> {
+    let s = s.trim();
+    let (digits, multiplier) = match s.chars().last() {
+        Some(c @ ('k' | 'K')) => (&s[..s.len() - c.len_utf8()], 1024),
+        Some(c @ ('m' | 'M')) => (&s[..s.len() - c.len_utf8()], 1024 * 1024),
+        Some(c @ ('g' | 'G')) => (&s[..s.len() - c.len_utf8()], 1024 * 1024 * 1024),
+        Some(c @ ('t' | 'T')) => (&s[..s.len() - c.len_utf8()], 1024 * 1024 * 1024 * 1024),
+        _ => (s, 1),
+    };
+    Ok(crate::parse_number(digits)? * multiplier)
+}
+
+pub(crate) struct VolumeReader {
+    base: PathBuf,
+    volume_size: u64,
+    position: u64,
+    current_index: Option<u64>,
+    current: Option<File>,
+}
+
+impl VolumeReader {
+    pub(crate) fn open(base: &Path) -> io::Result<Self> {
+        let volume_size = fs::metadata(volume_path(base, 0))?.len();
+        Ok(Self {
+            base: base.to_path_buf(),
+            volume_size,
+            position: 0,
+            current_index: None,
+            current: None,
+        })
+    }
+
+    fn ensure_current(&mut self, index: u64) -> io::Result<bool> {
+        if self.current_index == Some(index) {
+            return Ok(true);
+        }
+        let path = volume_path(&self.base, index);
+        if !path.is_file() {
+            return Ok(false);
+        }
+        self.current = Some(File::open(path)?);
+        self.current_index = Some(index);
+        Ok(true)
+    }
+}
+
+impl Read for VolumeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.volume_size == 0 {
+            return Ok(0);
+        }
+        let index = self.position / self.volume_size;
+        let intra_offset = self.position % self.volume_size;
+
+        if !self.ensure_current(index)? {
+            return Ok(0);
+        }
+
+        let remaining_in_volume = self.volume_size - intra_offset;
+        let to_read = (buf.len() as u64).min(remaining_in_volume) as usize;
+
+        let file = self.current.as_mut().expect("ensure_current populated current");
+        file.seek(SeekFrom::Start(intra_offset))?;
+        let read = file.read(&mut buf[..to_read])?;
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for VolumeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(delta) => (self.position as i64 + delta) as u64,
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "seek from end is not supported on split volumes",
+                ))
+            }
+        };
+        Ok(self.position)
+    }
+}
+
+pub(crate) struct VolumeWriter {
+    base: PathBuf,
+    volume_size: u64,
+    position: u64,
+    current_index: Option<u64>,
+    current: Option<File>,
+}
+
+impl VolumeWriter {
+    pub(crate) fn create(base: &Path, volume_size: u64) -> io::Result<Self> {
+        Ok(Self {
+            base: base.to_path_buf(),
+            volume_size,
+            position: 0,
+            current_index: None,
+            current: None,
+        })
+    }
+
+    fn ensure_current(&mut self, index: u64) -> io::Result<()> {
+        if self.current_index == Some(index) {
+            return Ok(());
+        }
+        // `truncate(false)` is deliberate: we may revisit an earlier volume
+        // (e.g. overlaying part data after the initial padding fill) and must
+        // not discard what was already written there.
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(volume_path(&self.base, index))?;
+        self.current = Some(file);
+        self.current_index = Some(index);
+        Ok(())
+    }
+}
+
+impl Write for VolumeWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.volume_size == 0 {
+            return Ok(0);
+        }
+        let index = self.position / self.volume_size;
+        let intra_offset = self.position % self.volume_size;
+        self.ensure_current(index)?;
+
+        let remaining_in_volume = self.volume_size - intra_offset;
+        let to_write = (buf.len() as u64).min(remaining_in_volume) as usize;
+
+        let file = self.current.as_mut().expect("ensure_current populated current");
+        file.seek(SeekFrom::Start(intra_offset))?;
+        let written = file.write(&buf[..to_write])?;
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current.as_mut() {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Seek for VolumeWriter {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match pos {
+            SeekFrom::Start(p) => p,
+            SeekFrom::Current(delta) => (self.position as i64 + delta) as u64,
+            SeekFrom::End(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "seek from end is not supported on split volumes",
+                ))
+            }
+        };
+        Ok(self.position)
+    }
+}
+
+pub(crate) fn logical_size(base: &Path) -> io::Result<u64> {
+    let mut total = 0u64;
+    let mut index = 0u64;
+    loop {
+        match fs::metadata(volume_path(base, index)) {
+            Ok(metadata) => {
+                total += metadata.len();
+                index += 1;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(total)
+}
+
+pub(crate) fn open_reader(firmware_path: &Path) -> io::Result<Box<dyn ReadSeek>> {
+    if is_split(firmware_path) {
+        Ok(Box::new(VolumeReader::open(firmware_path)?))
+    } else {
+        Ok(Box::new(File::open(firmware_path)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_read_round_trip_across_volumes() {
+        let base = Path::new("volume_rt");
+        let data = b"abcdefghijklmnopqrst";
+
+        let mut writer = VolumeWriter::create(base, 8).unwrap();
+        writer.write_all(data).unwrap();
+        drop(writer);
+
+        assert!(is_split(base));
+        assert_eq!(logical_size(base).unwrap(), data.len() as u64);
+
+        let mut reader = VolumeReader::open(base).unwrap();
+        let mut readback = Vec::new();
+        reader.read_to_end(&mut readback).unwrap();
+        assert_eq!(readback, data);
+
+        for index in 0.. {
+            let path = volume_path(base, index);
+            if !path.is_file() {
+                break;
+            }
+            fs::remove_file(path).unwrap();
+        }
+    }
+}